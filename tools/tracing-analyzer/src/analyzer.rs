@@ -5,11 +5,17 @@ use std::{
         Path,
         PathBuf,
     },
+    time::Duration,
 };
 
-use serde::Serialize;
+use serde::{
+    ser::SerializeMap,
+    Serialize,
+    Serializer,
+};
 
 use crate::{
+    budget,
     function_collector::FunctionCollector,
     tracing_collector::TracingCollector,
 };
@@ -29,11 +35,54 @@ pub struct FunctionInfo {
     pub end_line: usize,
     /// Number of tracing statements in this function
     pub tracing_count: usize,
-    /// Whether function has #[instrument] attribute
-    pub has_instrument: bool,
+    /// The instrument attribute covering this function, if any
+    ///
+    /// Serialized as `has_instrument` and `instrument_attr`, keeping the
+    /// `has_instrument` flag of the JSON output.
+    #[serde(flatten, serialize_with = "serialize_instrument")]
+    pub instrument_attr: Option<InstrumentAttr>,
+    /// Line of the item itself (after attributes)
+    #[serde(skip)]
+    pub item_line: usize,
+    /// Parameters to skip when instrumenting this function
+    #[serde(skip)]
+    pub skip_params: Vec<String>,
+    /// Number of log records attributed to this function at runtime
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime_hits: Option<usize>,
+    /// Maximum span duration declared via annotation or config
+    #[serde(skip)]
+    pub budget: Option<Duration>,
+    /// Busy durations of this function's spans measured from logs
+    #[serde(skip)]
+    pub span_durations: Vec<Duration>,
 }
 
 impl FunctionInfo {
+    /// Function spanning `start_line..=end_line` without any coverage yet
+    pub fn new(
+        file: impl Into<PathBuf>,
+        module_path: impl Into<String>,
+        name: impl Into<String>,
+        start_line: usize,
+        end_line: usize,
+    ) -> Self {
+        Self {
+            file: file.into(),
+            module_path: module_path.into(),
+            name: name.into(),
+            start_line,
+            end_line,
+            tracing_count: 0,
+            instrument_attr: None,
+            item_line: start_line,
+            skip_params: Vec::new(),
+            runtime_hits: None,
+            budget: None,
+            span_durations: Vec::new(),
+        }
+    }
+
     /// Calculate the number of lines in this function
     pub fn line_count(&self) -> usize {
         if self.end_line >= self.start_line {
@@ -53,6 +102,11 @@ impl FunctionInfo {
         }
     }
 
+    /// Whether function is covered by an instrument attribute
+    pub fn has_instrument(&self) -> bool {
+        self.instrument_attr.is_some()
+    }
+
     /// Get full qualified path
     pub fn full_path(&self) -> String {
         if self.module_path.is_empty() {
@@ -63,6 +117,16 @@ impl FunctionInfo {
     }
 }
 
+fn serialize_instrument<S: Serializer>(
+    instrument_attr: &Option<InstrumentAttr>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(2))?;
+    map.serialize_entry("has_instrument", &instrument_attr.is_some())?;
+    map.serialize_entry("instrument_attr", instrument_attr)?;
+    map.end()
+}
+
 /// Represents a tracing statement location
 #[derive(Debug, Clone, Serialize)]
 pub struct TracingLocation {
@@ -80,13 +144,51 @@ pub enum TracingKind {
     Instrument,
 }
 
+/// Span-producing attributes that count as coverage for a function
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+pub enum InstrumentAttr {
+    /// `#[instrument]` / `#[tracing::instrument]`
+    Instrument,
+    /// `#[instrument_sig]` on the function itself
+    InstrumentSig,
+    /// `#[instrument_trait_impl]` on the enclosing impl block
+    InstrumentTraitImpl,
+}
+
+impl InstrumentAttr {
+    /// Map an attribute name to its instrument kind
+    pub fn from_ident(ident: &str) -> Option<Self> {
+        match ident {
+            "instrument" => Some(Self::Instrument),
+            "instrument_sig" => Some(Self::InstrumentSig),
+            "instrument_trait_impl" => Some(Self::InstrumentTraitImpl),
+            _ => None,
+        }
+    }
+}
+
 /// Analyze a single Rust source file
-pub fn analyze_file(path: &Path) -> Result<Vec<FunctionInfo>, String> {
+///
+/// Functions covered by an instrument attribute are credited with
+/// `instrument_weight` tracing statements instead of the attribute line.
+pub fn analyze_file(
+    path: &Path,
+    instrument_weight: usize,
+) -> Result<Vec<FunctionInfo>, String> {
     let content = fs::read_to_string(path)
         .map_err(|e| format!("Failed to read file: {}", e))?;
 
+    analyze_source(path, &content, instrument_weight)
+}
+
+/// Analyze Rust source content attributed to `path`
+pub fn analyze_source(
+    path: &Path,
+    content: &str,
+    instrument_weight: usize,
+) -> Result<Vec<FunctionInfo>, String> {
     // Parse the file
-    let syntax = syn::parse_file(&content)
+    let syntax = syn::parse_file(content)
         .map_err(|e| format!("Failed to parse: {}", e))?;
 
     // Collect all functions
@@ -95,12 +197,16 @@ pub fn analyze_file(path: &Path) -> Result<Vec<FunctionInfo>, String> {
     let mut functions = function_collector.functions;
 
     // Collect all tracing statements (by line number)
-    let tracing_locations = TracingCollector::collect(&content);
+    let tracing_locations = TracingCollector::collect(content);
 
     // Build ordered map of line -> tracing statements
+    // Instrument attributes are credited from the AST below instead
     let tracing_map: BTreeMap<usize, Vec<&TracingLocation>> = {
         let mut map: BTreeMap<usize, Vec<&TracingLocation>> = BTreeMap::new();
-        for loc in &tracing_locations {
+        for loc in tracing_locations
+            .iter()
+            .filter(|loc| loc.kind != TracingKind::Instrument)
+        {
             map.entry(loc.line).or_default().push(loc);
         }
         map
@@ -116,8 +222,129 @@ pub fn analyze_file(path: &Path) -> Result<Vec<FunctionInfo>, String> {
             count += locs.len();
         }
 
+        if func.has_instrument() {
+            count += instrument_weight;
+        }
+
         func.tracing_count = count;
     }
 
+    budget::assign_annotations(&mut functions, content);
+
     Ok(functions)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(
+        content: &str,
+        instrument_weight: usize,
+    ) -> Vec<FunctionInfo> {
+        analyze_source(Path::new("lib.rs"), content, instrument_weight).unwrap()
+    }
+
+    #[test]
+    fn test_instrument_sig_counts_as_coverage() {
+        let content = r#"
+#[instrument_sig(skip(self))]
+fn example() {
+    let x = 1;
+}
+"#;
+        let functions = analyze(content, 1);
+        assert_eq!(functions.len(), 1);
+        assert_eq!(
+            functions[0].instrument_attr,
+            Some(InstrumentAttr::InstrumentSig)
+        );
+        assert_eq!(functions[0].tracing_count, 1);
+    }
+
+    #[test]
+    fn test_trait_impl_attribute_covers_methods() {
+        let content = r#"
+#[instrument_trait_impl]
+impl Foo for Bar {
+    fn first(&self) {
+        let x = 1;
+    }
+
+    fn second(&self) {
+        debug!("second");
+    }
+}
+"#;
+        let functions = analyze(content, 2);
+        assert_eq!(functions.len(), 2);
+        assert!(functions.iter().all(|f| f.has_instrument()));
+        assert_eq!(functions[0].tracing_count, 2);
+        assert_eq!(functions[1].tracing_count, 3);
+    }
+
+    #[test]
+    fn test_zero_weight_ignores_attributes() {
+        let content = r#"
+#[tracing::instrument]
+fn example() {
+    let x = 1;
+}
+"#;
+        let functions = analyze(content, 0);
+        assert_eq!(
+            functions[0].instrument_attr,
+            Some(InstrumentAttr::Instrument)
+        );
+        assert_eq!(functions[0].tracing_count, 0);
+    }
+
+    #[test]
+    fn test_skip_params() {
+        let content = r#"
+impl Foo {
+    fn other(&mut self, graph: &Hypergraph, n: usize) {
+        let x = 1;
+    }
+
+    fn run<T: Debug>(trav: HypergraphRef, value: T, name: &str) {
+        let x = 1;
+    }
+}
+"#;
+        let functions = analyze(content, 1);
+        assert_eq!(functions[0].skip_params, vec!["self", "graph"]);
+        assert_eq!(functions[1].skip_params, vec!["trav", "value"]);
+
+        let content = r#"
+impl<T: Clone> W<T> {
+    fn set(&self, value: T, n: usize) {
+        let x = 1;
+    }
+}
+
+trait Store<K> {
+    fn put(&mut self, key: K) {
+        let x = 1;
+    }
+}
+"#;
+        let functions = analyze(content, 1);
+        assert_eq!(functions[0].skip_params, vec!["self", "value"]);
+        assert_eq!(functions[1].skip_params, vec!["self", "key"]);
+    }
+
+    #[test]
+    fn test_json_keeps_has_instrument() {
+        let content = r#"
+#[instrument]
+fn example() {
+    let x = 1;
+}
+"#;
+        let json = serde_json::to_value(&analyze(content, 1)[0]).unwrap();
+        assert_eq!(json["has_instrument"], true);
+        assert_eq!(json["instrument_attr"], "Instrument");
+        assert_eq!(json["tracing_count"], 1);
+    }
+}
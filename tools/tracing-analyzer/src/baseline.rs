@@ -0,0 +1,267 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs,
+    path::Path,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::analyzer::FunctionInfo;
+
+/// Stored coverage snapshot used to detect regressions between runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Coverage per function, keyed by `file::qualified::name`
+    pub functions: BTreeMap<String, BaselineEntry>,
+}
+
+/// Coverage of a single function in a baseline
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BaselineEntry {
+    pub tracing_count: usize,
+    pub line_count: usize,
+    pub density: f64,
+}
+
+impl Baseline {
+    /// Build a baseline from analyzed functions
+    ///
+    /// File paths are stored relative to `root` so baselines stay valid
+    /// when the analyzer is invoked from another directory.
+    ///
+    /// Functions sharing a key, like `#[cfg]` variants of one function,
+    /// are numbered in source order as `key#2`, `key#3`, ... The colliding
+    /// keys are returned alongside the baseline so they can be reported.
+    pub fn from_functions(
+        functions: &[FunctionInfo],
+        root: &Path,
+    ) -> (Self, Vec<String>) {
+        let mut baseline = Self::default();
+        let mut duplicates = BTreeMap::<String, usize>::new();
+        for func in functions {
+            let file = func.file.strip_prefix(root).unwrap_or(&func.file);
+            let mut key = format!(
+                "{}::{}",
+                file.display().to_string().replace('\\', "/"),
+                func.full_path()
+            );
+            if baseline.functions.contains_key(&key) {
+                let count = duplicates.entry(key.clone()).or_insert(1);
+                *count += 1;
+                key = format!("{}#{}", key, count);
+            }
+            let entry = BaselineEntry {
+                tracing_count: func.tracing_count,
+                line_count: func.line_count(),
+                density: func.density(),
+            };
+            baseline.functions.insert(key, entry);
+        }
+        (baseline, duplicates.into_keys().collect())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read baseline: {}", e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse baseline: {}", e))
+    }
+
+    pub fn save(
+        &self,
+        path: &Path,
+    ) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize baseline: {}", e))?;
+        fs::write(path, content)
+            .map_err(|e| format!("Failed to write baseline: {}", e))
+    }
+
+    /// Compare `current` against this baseline
+    ///
+    /// Reports functions that have zero coverage now but were new or
+    /// covered in the baseline, and functions that lost tracing statements
+    /// or whose density dropped by more than `tolerance` percent of its
+    /// baseline density (e.g. from lines added around unchanged tracing).
+    pub fn regressions(
+        &self,
+        current: &Baseline,
+        tolerance: f64,
+    ) -> Vec<Regression> {
+        let mut regressions = Vec::new();
+        for (key, after) in &current.functions {
+            match self.functions.get(key) {
+                Some(before) if after.tracing_count == 0 =>
+                    if before.tracing_count > 0 {
+                        regressions.push(Regression::NewZero {
+                            key: key.clone(),
+                            before: Some(*before),
+                        });
+                    },
+                Some(before) =>
+                    if after.tracing_count < before.tracing_count
+                        || after.density
+                            < before.density * (1.0 - tolerance / 100.0)
+                    {
+                        regressions.push(Regression::DensityDropped {
+                            key: key.clone(),
+                            before: *before,
+                            after: *after,
+                        });
+                    },
+                None =>
+                    if after.tracing_count == 0 {
+                        regressions.push(Regression::NewZero {
+                            key: key.clone(),
+                            before: None,
+                        });
+                    },
+            }
+        }
+        regressions
+    }
+}
+
+/// A coverage regression relative to a baseline
+#[derive(Debug, Clone, PartialEq)]
+pub enum Regression {
+    /// Function still traced, but with lower density than before
+    DensityDropped {
+        key: String,
+        before: BaselineEntry,
+        after: BaselineEntry,
+    },
+    /// Function has zero coverage and was new or covered before
+    NewZero {
+        key: String,
+        before: Option<BaselineEntry>,
+    },
+}
+
+impl fmt::Display for Regression {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            Regression::DensityDropped { key, before, after } => write!(
+                f,
+                "density dropped  {} ({:.2} -> {:.2})",
+                key, before.density, after.density
+            ),
+            Regression::NewZero {
+                key,
+                before: Some(before),
+            } => write!(
+                f,
+                "lost coverage    {} ({} -> 0 statements)",
+                key, before.tracing_count
+            ),
+            Regression::NewZero { key, before: None } =>
+                write!(f, "new zero-cover   {}", key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_source;
+
+    fn entry(
+        tracing_count: usize,
+        line_count: usize,
+    ) -> BaselineEntry {
+        BaselineEntry {
+            tracing_count,
+            line_count,
+            density: (tracing_count as f64) / (line_count as f64) * 100.0,
+        }
+    }
+
+    fn baseline(entries: &[(&str, BaselineEntry)]) -> Baseline {
+        Baseline {
+            functions: entries
+                .iter()
+                .map(|(key, entry)| (key.to_string(), *entry))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_regressions() {
+        let before = baseline(&[
+            ("a.rs::kept", entry(2, 10)),
+            ("a.rs::diluted", entry(2, 10)),
+            ("a.rs::grown", entry(2, 40)),
+            ("a.rs::removed_one", entry(20, 100)),
+            ("a.rs::lost", entry(1, 10)),
+            ("a.rs::still_zero", entry(0, 10)),
+        ]);
+        let after = baseline(&[
+            ("a.rs::kept", entry(3, 10)),
+            ("a.rs::diluted", entry(2, 20)),
+            ("a.rs::grown", entry(2, 41)),
+            ("a.rs::removed_one", entry(19, 100)),
+            ("a.rs::lost", entry(0, 10)),
+            ("a.rs::still_zero", entry(0, 10)),
+            ("a.rs::added", entry(0, 5)),
+        ]);
+        let regressions = before.regressions(&after, 20.0);
+        assert_eq!(
+            regressions,
+            vec![
+                Regression::NewZero {
+                    key: "a.rs::added".to_string(),
+                    before: None,
+                },
+                Regression::DensityDropped {
+                    key: "a.rs::diluted".to_string(),
+                    before: entry(2, 10),
+                    after: entry(2, 20),
+                },
+                Regression::NewZero {
+                    key: "a.rs::lost".to_string(),
+                    before: Some(entry(1, 10)),
+                },
+                Regression::DensityDropped {
+                    key: "a.rs::removed_one".to_string(),
+                    before: entry(20, 100),
+                    after: entry(19, 100),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let content = r#"
+#[cfg(unix)]
+fn open() {
+    let x = 1;
+}
+
+#[cfg(not(unix))]
+fn open() {
+    debug!("open");
+}
+"#;
+        let functions =
+            analyze_source(Path::new("./src/fs.rs"), content, 1).unwrap();
+        let (baseline, duplicates) =
+            Baseline::from_functions(&functions, Path::new("."));
+        assert_eq!(duplicates, vec!["src/fs.rs::fs::open".to_string()]);
+        assert_eq!(
+            baseline.functions.keys().collect::<Vec<_>>(),
+            vec!["src/fs.rs::fs::open", "src/fs.rs::fs::open#2"]
+        );
+        assert_eq!(
+            baseline.functions["src/fs.rs::fs::open#2"].tracing_count,
+            1
+        );
+    }
+}
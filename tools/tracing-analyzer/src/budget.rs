@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use crate::analyzer::FunctionInfo;
+
+/// Comment marker declaring a span duration budget
+const BUDGET_MARKER: &str = "tracing-budget:";
+
+/// Parse a duration like `5ms`, `250us`, `12.5µs`, `1s` or `800ns`
+///
+/// This is also the format tracing-subscriber uses for `time.busy`.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value.parse().ok()?;
+    let nanos_per_unit = match unit.trim() {
+        "ns" => 1.0,
+        "us" | "µs" | "μs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return None,
+    };
+    Some(Duration::from_nanos((value * nanos_per_unit).round() as u64))
+}
+
+/// Assign `// tracing-budget: <duration>` annotations to functions
+///
+/// An annotation inside a function body applies to the innermost
+/// function containing it. An annotation outside of any function applies
+/// to the function starting directly below it (blank lines and other
+/// comments in between are allowed).
+pub fn assign_annotations(
+    functions: &mut [FunctionInfo],
+    content: &str,
+) {
+    let lines: Vec<&str> = content.lines().collect();
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        let Some(budget) = trimmed
+            .strip_prefix("//")
+            .and_then(|comment| comment.trim().strip_prefix(BUDGET_MARKER))
+            .and_then(parse_duration)
+        else {
+            continue;
+        };
+        let line_number = index + 1;
+
+        let innermost = functions
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| (f.start_line..=f.end_line).contains(&line_number))
+            .min_by_key(|(_, f)| f.line_count())
+            .map(|(i, _)| i);
+        let target = innermost.or_else(|| {
+            let next_code = lines[index + 1..]
+                .iter()
+                .position(|l| {
+                    let l = l.trim();
+                    let plain_comment =
+                        l.starts_with("//") && !l.starts_with("///");
+                    !l.is_empty() && !plain_comment
+                })
+                .map(|offset| line_number + 1 + offset)?;
+            functions.iter().position(|f| f.start_line == next_code)
+        });
+        if let Some(index) = target {
+            functions[index].budget = Some(budget);
+        }
+    }
+}
+
+/// A function whose measured span durations exceeded its budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetViolation {
+    pub function: String,
+    pub budget: Duration,
+    pub max: Duration,
+    /// Number of spans over budget
+    pub over: usize,
+    /// Number of measured spans
+    pub total: usize,
+}
+
+/// Check measured span durations against function budgets
+///
+/// Only durations attributed to a single function by
+/// [`correlate`](crate::runtime::correlate) are measured, so spans with a
+/// name shared by several functions never count against a budget.
+pub fn check(functions: &[FunctionInfo]) -> Vec<BudgetViolation> {
+    functions
+        .iter()
+        .filter_map(|func| {
+            let budget = func.budget?;
+            let max = func.span_durations.iter().max().copied()?;
+            (max > budget).then(|| BudgetViolation {
+                function: func.full_path(),
+                budget,
+                max,
+                over: func
+                    .span_durations
+                    .iter()
+                    .filter(|d| **d > budget)
+                    .count(),
+                total: func.span_durations.len(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        analyzer::analyze_source,
+        runtime::{
+            correlate,
+            LogRecord,
+        },
+    };
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5ms"), Some(Duration::from_millis(5)));
+        assert_eq!(
+            parse_duration("12.5µs"),
+            Some(Duration::from_nanos(12_500))
+        );
+        assert_eq!(parse_duration("250us"), Some(Duration::from_micros(250)));
+        assert_eq!(parse_duration(" 2s "), Some(Duration::from_secs(2)));
+        assert_eq!(parse_duration("5 minutes"), None);
+        assert_eq!(parse_duration("ms"), None);
+    }
+
+    #[test]
+    fn test_assign_annotations() {
+        let content = r#"
+// tracing-budget: 5ms
+/// Documented
+fn above() {
+    let x = 1;
+}
+
+fn inside() {
+    // tracing-budget: 250us
+    let x = 1;
+}
+
+// tracing-budget: 1ms
+const X: u8 = 0;
+fn unrelated() {
+    let x = 1;
+}
+"#;
+        let functions =
+            analyze_source(Path::new("lib.rs"), content, 1).unwrap();
+        let budgets: Vec<_> = functions.iter().map(|f| f.budget).collect();
+        assert_eq!(
+            budgets,
+            vec![
+                Some(Duration::from_millis(5)),
+                Some(Duration::from_micros(250)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_spans_not_measured() {
+        let content = |budget: &str| {
+            format!(
+                "impl T {{\n    {}\n    #[instrument]\n    fn new() {{\n        \
+                 let x = 1;\n    }}\n}}\n",
+                budget
+            )
+        };
+        let mut functions = analyze_source(
+            Path::new("a.rs"),
+            &content("// tracing-budget: 1ms"),
+            1,
+        )
+        .unwrap();
+        functions.extend(
+            analyze_source(Path::new("b.rs"), &content(""), 1).unwrap(),
+        );
+        let close = |filename: Option<&str>| LogRecord {
+            filename: filename.map(str::to_string),
+            line_number: filename.map(|_| 5),
+            span: Some("new".to_string()),
+            busy: Some(Duration::from_micros(9_500)),
+            ..LogRecord::default()
+        };
+
+        correlate(&mut functions, &[close(None)]);
+        assert!(check(&functions).is_empty());
+
+        correlate(&mut functions, &[close(Some("a.rs"))]);
+        let violations = check(&functions);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].function.starts_with("a::"));
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::Duration,
+};
+
+use globset::{
+    Glob,
+    GlobMatcher,
+    GlobSet,
+    GlobSetBuilder,
+};
+use serde::Deserialize;
+
+use crate::{
+    analyzer::FunctionInfo,
+    budget::parse_duration,
+};
+
+/// Default config file name, looked up in the analyzed directory
+pub const CONFIG_FILE_NAME: &str = "tracing-analyzer.toml";
+
+/// Contents of a `tracing-analyzer.toml` file
+///
+/// ```toml
+/// # Path globs (relative to the analyzed directory) to skip
+/// exclude = ["**/tests/**", "**/generated/**"]
+/// # Function paths (module::Type::name globs) to drop from results
+/// exempt = ["*::fmt", "graph::Hypergraph::new"]
+///
+/// # Minimum tracing density (statements per 100 lines) of all functions
+/// # in the files below a directory or matching a path glob
+/// [min_density]
+/// "crates/context-search" = 2.0
+/// "**/graph/*.rs" = 1.5
+///
+/// # Maximum span duration per function path glob
+/// [budgets]
+/// "*::SearchState::next" = "5ms"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    exclude: Vec<String>,
+    exempt: Vec<String>,
+    min_density: BTreeMap<String, f64>,
+    budgets: BTreeMap<String, String>,
+}
+
+/// Include/exclude rules and density targets for an analyzer run
+#[derive(Debug, Clone)]
+pub struct Config {
+    exclude: GlobSet,
+    exempt: GlobSet,
+    /// Minimum density per path glob, matched against files and their
+    /// parent directories
+    min_density: Vec<(String, GlobMatcher, f64)>,
+    /// Span duration budgets, parallel to the patterns in `budget_globs`
+    budgets: Vec<Duration>,
+    budget_globs: GlobSet,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            exclude: GlobSet::empty(),
+            exempt: GlobSet::empty(),
+            min_density: Vec::new(),
+            budgets: Vec::new(),
+            budget_globs: GlobSet::empty(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config: {}", e))?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(content)
+            .map_err(|e| format!("Failed to parse config: {}", e))?;
+        let mut min_density = Vec::new();
+        for (pattern, target) in file.min_density {
+            let matcher = Glob::new(&pattern)
+                .map_err(|e| format!("Invalid glob {:?}: {}", pattern, e))?
+                .compile_matcher();
+            min_density.push((pattern, matcher, target));
+        }
+        let mut budget_patterns = Vec::new();
+        let mut budgets = Vec::new();
+        for (pattern, budget) in file.budgets {
+            let budget = parse_duration(&budget).ok_or_else(|| {
+                format!("Invalid budget {:?} for {:?}", budget, pattern)
+            })?;
+            budget_patterns.push(pattern);
+            budgets.push(budget);
+        }
+        Ok(Self {
+            exclude: build_glob_set(&file.exclude)?,
+            exempt: build_glob_set(&file.exempt)?,
+            min_density,
+            budgets,
+            budget_globs: build_glob_set(&budget_patterns)?,
+        })
+    }
+
+    /// Configured span duration budget of a function, if any
+    ///
+    /// When several patterns match, the tightest budget wins.
+    pub fn budget_for(
+        &self,
+        func: &FunctionInfo,
+    ) -> Option<Duration> {
+        self.budget_globs
+            .matches(func.full_path())
+            .into_iter()
+            .map(|index| self.budgets[index])
+            .min()
+    }
+
+    /// Whether `path` (relative to the analyzed root) is excluded
+    pub fn is_excluded(
+        &self,
+        path: &Path,
+    ) -> bool {
+        self.exclude.is_match(path)
+    }
+
+    /// Whether a function is explicitly exempted from tracing
+    pub fn is_exempt(
+        &self,
+        func: &FunctionInfo,
+    ) -> bool {
+        self.exempt.is_match(func.full_path())
+    }
+
+    /// Evaluate the density targets against `functions`
+    ///
+    /// Function files are matched relative to `root`. Targets matching no
+    /// function (e.g. outside of a partial run, or emptied by `exclude`)
+    /// have no density and are not checked.
+    pub fn check_targets(
+        &self,
+        functions: &[FunctionInfo],
+        root: &Path,
+    ) -> Vec<DensityTarget> {
+        self.min_density
+            .iter()
+            .map(|(pattern, matcher, target)| {
+                let members: Vec<&FunctionInfo> = functions
+                    .iter()
+                    .filter(|f| {
+                        let file = f.file.strip_prefix(root).unwrap_or(&f.file);
+                        file.ancestors().any(|path| matcher.is_match(path))
+                    })
+                    .collect();
+                let tracing: usize =
+                    members.iter().map(|f| f.tracing_count).sum();
+                let lines: usize = members.iter().map(|f| f.line_count()).sum();
+                let density = (!members.is_empty()).then(|| {
+                    if lines > 0 {
+                        (tracing as f64) / (lines as f64) * 100.0
+                    } else {
+                        0.0
+                    }
+                });
+                DensityTarget {
+                    pattern: pattern.clone(),
+                    target: *target,
+                    density,
+                    functions: members.len(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Result of checking one density target against its minimum density
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityTarget {
+    /// Path glob of the target as written in the config file
+    pub pattern: String,
+    pub target: f64,
+    /// Density of the matched functions, `None` if nothing matched
+    pub density: Option<f64>,
+    pub functions: usize,
+}
+
+impl DensityTarget {
+    /// Whether the target holds; targets without functions always do
+    pub fn is_met(&self) -> bool {
+        self.density.is_none_or(|density| density >= self.target)
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern)
+            .map_err(|e| format!("Invalid glob {:?}: {}", pattern, e))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build glob set: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(
+        module_path: &str,
+        name: &str,
+        tracing_count: usize,
+    ) -> FunctionInfo {
+        FunctionInfo {
+            tracing_count,
+            ..FunctionInfo::new("lib.rs", module_path, name, 1, 10)
+        }
+    }
+
+    #[test]
+    fn test_exclude_and_exempt() {
+        let config = Config::parse(
+            r#"
+exclude = ["**/tests/**"]
+exempt = ["*::fmt"]
+"#,
+        )
+        .unwrap();
+        assert!(config.is_excluded(Path::new("crate/tests/search.rs")));
+        assert!(!config.is_excluded(Path::new("crate/src/search.rs")));
+        assert!(config.is_exempt(&function(
+            "graph::<Fooasfmt::Debug>",
+            "fmt",
+            0
+        )));
+        assert!(!config.is_exempt(&function("graph", "insert", 0)));
+    }
+
+    #[test]
+    fn test_module_targets() {
+        let config = Config::parse(
+            r#"
+[min_density]
+"search" = 15.0
+"insert/*.rs" = 5.0
+"#,
+        )
+        .unwrap();
+        let function = |file: &str, tracing_count| FunctionInfo {
+            tracing_count,
+            ..FunctionInfo::new(file, "", "f", 1, 10)
+        };
+        let functions = [
+            function("./search/lib.rs", 1),
+            function("./search/state/mod.rs", 1),
+            function("./insert/lib.rs", 1),
+            function("./searcher/lib.rs", 5),
+        ];
+        let root = Path::new(".");
+        let targets = config.check_targets(&functions, root);
+        assert_eq!(targets.len(), 2);
+        let insert = &targets[0];
+        assert_eq!(insert.pattern, "insert/*.rs");
+        assert!(insert.is_met());
+        let search = &targets[1];
+        assert_eq!(search.functions, 2);
+        assert!(!search.is_met());
+
+        let missing =
+            Config::parse("[min_density]\n\"context_search\" = 2.0").unwrap();
+        let missing = &missing.check_targets(&functions, root)[0];
+        assert_eq!(missing.density, None);
+        assert!(missing.is_met());
+    }
+
+    #[test]
+    fn test_budgets() {
+        let config = Config::parse(
+            r#"
+[budgets]
+"search::*" = "5ms"
+"search::next" = "250us"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.budget_for(&function("search", "next", 0)),
+            Some(Duration::from_micros(250))
+        );
+        assert_eq!(
+            config.budget_for(&function("search", "find", 0)),
+            Some(Duration::from_millis(5))
+        );
+        assert_eq!(config.budget_for(&function("insert", "run", 0)), None);
+        assert!(Config::parse("[budgets]\n\"a\" = \"fast\"").is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        assert!(Config::parse("excludes = []").is_err());
+    }
+}
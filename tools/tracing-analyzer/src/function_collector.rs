@@ -1,21 +1,37 @@
-use std::path::{
-    Path,
-    PathBuf,
+use std::{
+    collections::HashSet,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
 use syn::{
     spanned::Spanned,
     Attribute,
     File,
+    FnArg,
+    Generics,
     ImplItem,
     Item,
     ItemFn,
     ItemImpl,
     ItemMod,
+    Pat,
+    Signature,
     TraitItem,
+    Type,
+    Visibility,
 };
 
-use crate::analyzer::FunctionInfo;
+use crate::analyzer::{
+    FunctionInfo,
+    InstrumentAttr,
+};
+
+/// Lowercase type name fragments of context values too heavy to record in
+/// spans (matching e.g. `Hypergraph`, `HypergraphRef` and `TraceCtx`)
+const HEAVY_TYPES: &[&str] = &["graph", "trav", "cache", "ctx", "context"];
 
 /// Collects all function definitions from a Rust file
 pub struct FunctionCollector {
@@ -75,32 +91,100 @@ impl FunctionCollector {
         self.module_stack.join("::")
     }
 
-    fn has_instrument_attr(attrs: &[Attribute]) -> bool {
-        attrs.iter().any(|attr| {
-            attr.path()
-                .segments
-                .iter()
-                .any(|seg| seg.ident == "instrument")
+    fn instrument_attr(attrs: &[Attribute]) -> Option<InstrumentAttr> {
+        attrs.iter().find_map(|attr| {
+            attr.path().segments.last().and_then(|seg| {
+                InstrumentAttr::from_ident(&seg.ident.to_string())
+            })
         })
     }
 
+    /// First line of an item after its attributes
+    fn item_line(
+        vis: &Visibility,
+        sig: &Signature,
+    ) -> usize {
+        match vis {
+            Visibility::Inherited => sig.span().start().line,
+            _ => vis.span().start().line,
+        }
+    }
+
+    /// Infer which parameters should be skipped by a span attribute
+    ///
+    /// Receivers, mutable references, trait objects, generic parameters
+    /// (of the function and of its enclosing impl or trait) and
+    /// graph/context types are skipped since they are either not `Debug`
+    /// or too large to record.
+    fn skip_params(
+        sig: &Signature,
+        outer_generics: Option<&Generics>,
+    ) -> Vec<String> {
+        let generics: HashSet<String> = outer_generics
+            .into_iter()
+            .chain([&sig.generics])
+            .flat_map(Generics::type_params)
+            .map(|param| param.ident.to_string())
+            .collect();
+        sig.inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                FnArg::Receiver(_) => Some("self".to_string()),
+                FnArg::Typed(pat_type) => match &*pat_type.pat {
+                    Pat::Ident(pat_ident)
+                        if Self::needs_skip(&pat_type.ty, &generics) =>
+                        Some(pat_ident.ident.to_string()),
+                    _ => None,
+                },
+            })
+            .collect()
+    }
+
+    fn needs_skip(
+        ty: &Type,
+        generics: &HashSet<String>,
+    ) -> bool {
+        match ty {
+            Type::Reference(reference) =>
+                reference.mutability.is_some()
+                    || Self::needs_skip(&reference.elem, generics),
+            Type::Paren(paren) => Self::needs_skip(&paren.elem, generics),
+            Type::Group(group) => Self::needs_skip(&group.elem, generics),
+            Type::ImplTrait(_) | Type::TraitObject(_) | Type::BareFn(_) => true,
+            Type::Path(type_path) => {
+                let is_generic = type_path.qself.is_none()
+                    && type_path.path.get_ident().is_some_and(|ident| {
+                        generics.contains(&ident.to_string())
+                    });
+                let name = quote::quote!(#type_path).to_string().to_lowercase();
+                is_generic
+                    || HEAVY_TYPES.iter().any(|heavy| name.contains(heavy))
+            },
+            _ => false,
+        }
+    }
+
     fn add_function(
         &mut self,
-        name: &str,
-        attrs: &[Attribute],
+        sig: &Signature,
+        outer_generics: Option<&Generics>,
+        instrument_attr: Option<InstrumentAttr>,
         start_line: usize,
+        item_line: usize,
         end_line: usize,
     ) {
-        let has_instrument = Self::has_instrument_attr(attrs);
-
+        // Tracing counts, budgets and runtime data are filled in later
         self.functions.push(FunctionInfo {
-            file: self.file_path.clone(),
-            module_path: self.current_module_path(),
-            name: name.to_string(),
-            start_line,
-            end_line,
-            tracing_count: 0, // Will be filled in later
-            has_instrument,
+            instrument_attr,
+            item_line,
+            skip_params: Self::skip_params(sig, outer_generics),
+            ..FunctionInfo::new(
+                self.file_path.clone(),
+                self.current_module_path(),
+                sig.ident.to_string(),
+                start_line,
+                end_line,
+            )
         });
     }
 
@@ -137,10 +221,13 @@ impl FunctionCollector {
                                 .as_ref()
                                 .map(|b| b.span().end().line)
                                 .unwrap_or(start);
+                            let item = method.sig.span().start().line;
                             self.add_function(
-                                &method.sig.ident.to_string(),
-                                &method.attrs,
+                                &method.sig,
+                                Some(&item_trait.generics),
+                                Self::instrument_attr(&method.attrs),
                                 start,
+                                item,
                                 end,
                             );
                         }
@@ -161,12 +248,15 @@ impl FunctionCollector {
             .first()
             .map(|a| a.span().start().line)
             .unwrap_or_else(|| item_fn.sig.span().start().line);
+        let item = Self::item_line(&item_fn.vis, &item_fn.sig);
         let end = item_fn.block.span().end().line;
 
         self.add_function(
-            &item_fn.sig.ident.to_string(),
-            &item_fn.attrs,
+            &item_fn.sig,
+            None,
+            Self::instrument_attr(&item_fn.attrs),
             start,
+            item,
             end,
         );
 
@@ -213,6 +303,9 @@ impl FunctionCollector {
 
         self.module_stack.push(impl_name);
 
+        // #[instrument_trait_impl] on the impl block covers every method
+        let impl_instrument = Self::instrument_attr(&item_impl.attrs);
+
         for item in &item_impl.items {
             if let ImplItem::Fn(method) = item {
                 let start = method
@@ -220,12 +313,15 @@ impl FunctionCollector {
                     .first()
                     .map(|a| a.span().start().line)
                     .unwrap_or_else(|| method.sig.span().start().line);
+                let item = Self::item_line(&method.vis, &method.sig);
                 let end = method.block.span().end().line;
 
                 self.add_function(
-                    &method.sig.ident.to_string(),
-                    &method.attrs,
+                    &method.sig,
+                    Some(&item_impl.generics),
+                    Self::instrument_attr(&method.attrs).or(impl_instrument),
                     start,
+                    item,
                     end,
                 );
             }
@@ -0,0 +1,252 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use crate::analyzer::FunctionInfo;
+
+/// Inline script making every `table.sortable` sortable by header click
+///
+/// Columns are numbered per table, so each table sorts by its own cells.
+const SORT_SCRIPT: &str = r#"
+document.querySelectorAll('table.sortable').forEach(table => {
+  table.querySelectorAll('th').forEach((th, col) => {
+    th.addEventListener('click', () => {
+      const body = table.tBodies[0];
+      const asc = th.dataset.order !== 'asc';
+      table.querySelectorAll('th').forEach(h => delete h.dataset.order);
+      th.dataset.order = asc ? 'asc' : 'desc';
+      const key = row => {
+        const cell = row.cells[col];
+        const value = cell.dataset.value ?? cell.textContent;
+        const num = parseFloat(value);
+        return isNaN(num) ? value : num;
+      };
+      [...body.rows]
+        .sort((a, b) => {
+          const x = key(a), y = key(b);
+          const cmp = x < y ? -1 : x > y ? 1 : 0;
+          return asc ? cmp : -cmp;
+        })
+        .forEach(row => body.appendChild(row));
+    });
+  });
+});
+"#;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; width: 100%; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th[data-order=asc]::after { content: " \25B2"; }
+th[data-order=desc]::after { content: " \25BC"; }
+td.num { text-align: right; font-family: monospace; }
+tr.zero td { background: #fdecea; }
+details { margin-bottom: 0.5em; }
+summary { cursor: pointer; font-family: monospace; }
+"#;
+
+/// Render a self-contained HTML coverage report
+///
+/// Functions are grouped per source file, i.e. per module file, each
+/// group rendered as a collapsible sortable table with links to the
+/// function's source lines.
+pub fn render(functions: &[FunctionInfo]) -> String {
+    let mut files: BTreeMap<&Path, Vec<&FunctionInfo>> = BTreeMap::new();
+    for func in functions {
+        files.entry(func.file.as_path()).or_default().push(func);
+    }
+
+    let mut html = String::new();
+    html.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+    );
+    html.push_str("<title>Tracing coverage report</title>\n");
+    let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+    html.push_str("<h1>Tracing coverage report</h1>\n");
+
+    // File overview
+    html.push_str("<h2>Files</h2>\n<table class=\"sortable\">\n<thead><tr>");
+    html.push_str(
+        "<th>File</th><th>Functions</th><th>Zero</th><th>Count</th>\
+         <th>Density</th>",
+    );
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for (file, funcs) in &files {
+        let stats = GroupStats::new(funcs);
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"#{}\">{}</a></td>{}</tr>",
+            anchor(file),
+            escape(&file.display().to_string()),
+            stats.cells(),
+        );
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    // Per-file drill-down
+    html.push_str("<h2>Functions</h2>\n");
+    for (file, funcs) in &files {
+        let stats = GroupStats::new(funcs);
+        let _ = writeln!(
+            html,
+            "<details id=\"{}\"{}>\n<summary>{} ({} functions, {} zero, \
+             {:.2}%)</summary>",
+            anchor(file),
+            if stats.zero > 0 { " open" } else { "" },
+            escape(&file.display().to_string()),
+            stats.functions,
+            stats.zero,
+            stats.density(),
+        );
+        html.push_str("<table class=\"sortable\">\n<thead><tr>");
+        html.push_str(
+            "<th>Function</th><th>Lines</th><th>Count</th><th>Density</th>\
+             <th>Instrumented</th>",
+        );
+        html.push_str("</tr></thead>\n<tbody>\n");
+        for func in funcs {
+            let _ = writeln!(
+                html,
+                "<tr{}><td><a href=\"{}\">{}</a></td>\
+                 <td class=\"num\" data-value=\"{}\">{}-{}</td>\
+                 <td class=\"num\">{}</td><td class=\"num\">{:.2}</td>\
+                 <td>{}</td></tr>",
+                if func.tracing_count == 0 {
+                    " class=\"zero\""
+                } else {
+                    ""
+                },
+                source_link(&func.file, func.start_line),
+                escape(&func.full_path()),
+                func.start_line,
+                func.start_line,
+                func.end_line,
+                func.tracing_count,
+                func.density(),
+                if func.has_instrument() { "yes" } else { "" },
+            );
+        }
+        html.push_str("</tbody>\n</table>\n</details>\n");
+    }
+
+    let _ =
+        writeln!(html, "<script>{}</script>\n</body>\n</html>", SORT_SCRIPT);
+    html
+}
+
+/// Aggregated coverage numbers of a function group
+struct GroupStats {
+    functions: usize,
+    zero: usize,
+    tracing: usize,
+    lines: usize,
+}
+
+impl GroupStats {
+    fn new(funcs: &[&FunctionInfo]) -> Self {
+        Self {
+            functions: funcs.len(),
+            zero: funcs.iter().filter(|f| f.tracing_count == 0).count(),
+            tracing: funcs.iter().map(|f| f.tracing_count).sum(),
+            lines: funcs.iter().map(|f| f.line_count()).sum(),
+        }
+    }
+
+    fn density(&self) -> f64 {
+        if self.lines == 0 {
+            0.0
+        } else {
+            (self.tracing as f64) / (self.lines as f64) * 100.0
+        }
+    }
+
+    fn cells(&self) -> String {
+        format!(
+            "<td class=\"num\">{}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{}</td><td class=\"num\">{:.2}</td>",
+            self.functions,
+            self.zero,
+            self.tracing,
+            self.density()
+        )
+    }
+}
+
+/// Editor link opening `file` at `line`
+fn source_link(
+    file: &Path,
+    line: usize,
+) -> String {
+    let path: PathBuf =
+        std::fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+    let path = path.display().to_string().replace('\\', "/");
+    escape(&format!(
+        "vscode://file/{}:{}",
+        path.trim_start_matches('/'),
+        line
+    ))
+}
+
+fn anchor(file: &Path) -> String {
+    file.display()
+        .to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(
+        file: &str,
+        module_path: &str,
+        name: &str,
+        tracing_count: usize,
+    ) -> FunctionInfo {
+        FunctionInfo {
+            tracing_count,
+            ..FunctionInfo::new(file, module_path, name, 1, 10)
+        }
+    }
+
+    #[test]
+    fn test_render_groups_and_escapes() {
+        let html = render(&[
+            function("src/a.rs", "a::<Fooas\"Bar\">", "fmt", 0),
+            function("src/b.rs", "b", "run", 2),
+            function("src/a.rs", "a", "new", 1),
+        ]);
+        assert_eq!(html.matches("<details ").count(), 2);
+        assert!(html.contains(
+            "<summary>src/a.rs (2 functions, 1 zero, 5.00%)</summary>"
+        ));
+        assert!(html.contains(
+            "<summary>src/b.rs (1 functions, 0 zero, 20.00%)</summary>"
+        ));
+        assert!(html.contains("a::&lt;Fooas&quot;Bar&quot;&gt;::fmt"));
+        assert!(!html.contains("<Fooas"));
+    }
+}
@@ -2,17 +2,34 @@ use clap::{
     CommandFactory,
     Parser,
 };
-use std::path::{
-    Path,
-    PathBuf,
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 use walkdir::WalkDir;
 
 mod analyzer;
+mod baseline;
+mod budget;
+mod config;
 mod function_collector;
+mod html_report;
+mod runtime;
+mod suggest;
 mod tracing_collector;
+mod watch;
 
 use analyzer::analyze_file;
+use baseline::Baseline;
+use config::{
+    Config,
+    CONFIG_FILE_NAME,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "tracing-analyzer")]
@@ -24,10 +41,14 @@ struct Args {
     #[arg(default_value = ".")]
     path: PathBuf,
 
-    /// Output format: text, json, or csv
+    /// Output format: text, json, csv, or html
     #[arg(short, long, default_value = "text")]
     format: String,
 
+    /// Write the report to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
     /// Sort by: name, count, density
     #[arg(short, long, default_value = "density")]
     sort: String,
@@ -39,6 +60,49 @@ struct Args {
     /// Minimum function line count to include
     #[arg(long, default_value = "3")]
     min_lines: usize,
+
+    /// Tracing statements credited to functions covered by
+    /// #[instrument], #[instrument_sig] or #[instrument_trait_impl]
+    #[arg(long, default_value = "1")]
+    instrument_weight: usize,
+
+    /// Compare the run against a baseline file and report regressions
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Write the current run to this baseline file
+    #[arg(long)]
+    write_baseline: Option<PathBuf>,
+
+    /// Density drop (in percent of the baseline density) tolerated for
+    /// functions that kept all their tracing statements
+    #[arg(long, default_value = "20")]
+    density_tolerance: f64,
+
+    /// Number of baseline regressions tolerated before exiting non-zero
+    #[arg(long, default_value = "0")]
+    max_regressions: usize,
+
+    /// Emit a unified diff adding #[instrument_sig(skip(..))] to
+    /// zero-coverage functions instead of the report (use with --output).
+    /// The diff adds no imports; files that don't import instrument_sig
+    /// yet need a `use` added by hand
+    #[arg(long)]
+    suggest: bool,
+
+    /// JSON log file(s) of a run to correlate with instrumented functions
+    #[arg(long = "log", value_name = "FILE")]
+    logs: Vec<PathBuf>,
+
+    /// Keep running and print coverage deltas whenever a source file
+    /// changes
+    #[arg(long)]
+    watch: bool,
+
+    /// Config file with exclude/exempt rules and path density targets
+    /// (defaults to tracing-analyzer.toml in the analyzed directory)
+    #[arg(long)]
+    config: Option<PathBuf>,
 }
 
 fn main() {
@@ -50,14 +114,47 @@ fn main() {
 
     let args = Args::parse();
 
-    let source_files = collect_source_files(&args.path);
-    println!("Found {} source files to analyze", source_files.len());
+    // Keep stdout clean when it carries a machine-readable report
+    let to_stderr =
+        args.output.is_none() && (args.suggest || args.format != "text");
+    let status = |text: &str| {
+        if to_stderr {
+            eprint!("{}", text);
+        } else {
+            print!("{}", text);
+        }
+    };
+
+    let config = match load_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        },
+    };
+
+    let source_files = collect_source_files(&args.path, &config);
+    // Baseline keys and targets use paths relative to the analyzed
+    // directory, which for a single file is the directory containing it
+    let root = if args.path.is_file() {
+        args.path.parent().unwrap_or(Path::new(""))
+    } else {
+        args.path.as_path()
+    };
+    status(&format!(
+        "Found {} source files to analyze\n",
+        source_files.len()
+    ));
 
     let mut all_functions = Vec::new();
+    let mut watch_state = BTreeMap::new();
 
     for file_path in &source_files {
-        match analyze_file(file_path) {
+        match analyze_path(file_path, &args, &config) {
             Ok(functions) => {
+                if args.watch {
+                    watch_state.insert(file_path.clone(), functions.clone());
+                }
                 all_functions.extend(functions);
             },
             Err(e) => {
@@ -66,8 +163,71 @@ fn main() {
         }
     }
 
-    // Filter by minimum lines
-    all_functions.retain(|f| f.line_count() >= args.min_lines);
+    // Correlate with runtime logs
+    let mut runtime_report = None;
+    let mut violations = None;
+    if !args.logs.is_empty() {
+        let mut records = Vec::new();
+        for path in &args.logs {
+            match runtime::read_log(path) {
+                Ok((log, skipped)) => {
+                    if skipped > 0 {
+                        eprintln!(
+                            "Warning: skipped {} unparsable records in {:?}",
+                            skipped, path
+                        );
+                    }
+                    records.extend(log);
+                },
+                Err(e) => {
+                    eprintln!("Error reading {:?}: {}", path, e);
+                    std::process::exit(2);
+                },
+            }
+        }
+        let ambiguous = runtime::correlate(&mut all_functions, &records);
+        for filename in &ambiguous.files {
+            eprintln!(
+                "Warning: log file {} matches several analyzed files, \
+                 attributing its records by span name only",
+                filename
+            );
+        }
+        for span in &ambiguous.spans {
+            eprintln!(
+                "Warning: span {} matches several instrumented functions, \
+                 ignoring its records without a resolvable location",
+                span
+            );
+        }
+        // Runtime coverage and budgets cover all functions, not just the
+        // displayed ones
+        runtime_report = Some(runtime_coverage(&all_functions));
+        if all_functions.iter().any(|f| f.budget.is_some()) {
+            violations = Some(budget::check(&all_functions));
+        }
+    }
+
+    let (current, duplicates) = Baseline::from_functions(&all_functions, root);
+    for key in &duplicates {
+        eprintln!("Warning: several functions share the baseline key {}", key);
+    }
+    if let Some(path) = &args.write_baseline {
+        match current.save(path) {
+            Ok(()) =>
+                status(&format!("Wrote baseline to {}\n", path.display())),
+            Err(e) => eprintln!("Error writing {:?}: {}", path, e),
+        }
+    }
+
+    // Density targets apply to all functions, not just the displayed ones
+    let targets = config.check_targets(&all_functions, root);
+    for target in targets.iter().filter(|t| t.density.is_none()) {
+        eprintln!(
+            "Warning: density target {:?} matches no analyzed function",
+            target.pattern
+        );
+    }
 
     // Filter by zero-only if requested
     if args.zero_only {
@@ -78,7 +238,7 @@ fn main() {
     match args.sort.as_str() {
         "name" => all_functions.sort_by_key(|a| a.full_path()),
         "count" =>
-            all_functions.sort_by(|a, b| b.tracing_count.cmp(&a.tracing_count)),
+            all_functions.sort_by_key(|f| std::cmp::Reverse(f.tracing_count)),
         _ => all_functions.sort_by(|a, b| {
             b.density()
                 .partial_cmp(&a.density())
@@ -87,47 +247,173 @@ fn main() {
     }
 
     // Output
-    match args.format.as_str() {
+    let report = match args.format.as_str() {
+        _ if args.suggest => match suggest::unified_diff(&all_functions) {
+            Ok(diff) => diff,
+            Err(e) => {
+                eprintln!("Error building suggestions: {}", e);
+                std::process::exit(2);
+            },
+        },
         "json" => output_json(&all_functions),
         "csv" => output_csv(&all_functions),
+        "html" => html_report::render(&all_functions),
         _ => output_text(&all_functions),
+    };
+    match &args.output {
+        Some(path) => match fs::write(path, report) {
+            Ok(()) => status(&format!("Wrote report to {}\n", path.display())),
+            Err(e) => eprintln!("Error writing {:?}: {}", path, e),
+        },
+        None => print!("{}", report),
     }
 
     // Summary statistics
-    print_summary(&all_functions);
+    status(&summary(&all_functions));
+
+    let mut failed = false;
+
+    if let Some(runtime_report) = &runtime_report {
+        status(runtime_report);
+    }
+
+    if let Some(violations) = &violations {
+        status(&budget_violations(violations));
+        failed |= !violations.is_empty();
+    }
+
+    if !targets.is_empty() {
+        status(&density_targets(&targets));
+        failed |= targets.iter().any(|t| !t.is_met());
+    }
+
+    if let Some(path) = &args.baseline {
+        let baseline = match Baseline::load(path) {
+            Ok(baseline) => baseline,
+            Err(e) => {
+                eprintln!("Error loading {:?}: {}", path, e);
+                std::process::exit(2);
+            },
+        };
+        let regressions =
+            baseline.regressions(&current, args.density_tolerance);
+        status(&baseline_regressions(&regressions));
+        failed |= regressions.len() > args.max_regressions;
+    }
+
+    if args.watch {
+        let result = watch::run(
+            &args.path,
+            watch_state,
+            |file| analyze_path(file, &args, &config),
+            |relative| is_source_file(relative, &config),
+        );
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
 }
 
-fn collect_source_files(path: &Path) -> Vec<PathBuf> {
+/// Analyze one file, dropping short and exempted functions
+fn analyze_path(
+    file: &Path,
+    args: &Args,
+    config: &Config,
+) -> Result<Vec<analyzer::FunctionInfo>, String> {
+    let mut functions = analyze_file(file, args.instrument_weight)?;
+    functions.retain(|f| f.line_count() >= args.min_lines);
+    functions.retain(|f| !config.is_exempt(f));
+    for func in &mut functions {
+        // Annotations in the source take precedence over config entries
+        if func.budget.is_none() {
+            func.budget = config.budget_for(func);
+        }
+    }
+    Ok(functions)
+}
+
+fn load_config(args: &Args) -> Result<Config, String> {
+    match &args.config {
+        Some(path) => Config::load(path)
+            .map_err(|e| format!("Error loading {:?}: {}", path, e)),
+        None => {
+            let path = args.path.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                Config::load(&path)
+                    .map_err(|e| format!("Error loading {:?}: {}", path, e))
+            } else {
+                Ok(Config::default())
+            }
+        },
+    }
+}
+
+fn collect_source_files(
+    path: &Path,
+    config: &Config,
+) -> Vec<PathBuf> {
     let mut files = Vec::new();
 
     for entry in WalkDir::new(path)
         .into_iter()
         .filter_entry(|e| {
-            let name = e.file_name().to_string_lossy();
-            // Skip target, hidden directories, and deps
-            !name.starts_with('.') && name != "target" && name != "deps"
+            e.depth() == 0 || !is_skipped_dir(&e.file_name().to_string_lossy())
         })
         .filter_map(|e| e.ok())
     {
-        let path = entry.path();
-        if path.is_file() && path.extension().is_some_and(|ext| ext == "rs") {
-            files.push(path.to_path_buf());
+        let file = entry.path();
+        // A single file given as path is matched by its file name
+        let relative = if entry.depth() == 0 {
+            Path::new(entry.file_name())
+        } else {
+            file.strip_prefix(path).unwrap_or(file)
+        };
+        if file.is_file() && is_source_file(relative, config) {
+            files.push(file.to_path_buf());
         }
     }
 
     files
 }
 
-fn output_text(functions: &[analyzer::FunctionInfo]) {
-    println!("\n{:-<100}", "");
-    println!(
+/// Skip target, hidden directories, and deps
+fn is_skipped_dir(name: &str) -> bool {
+    name.starts_with('.') || name == "target" || name == "deps"
+}
+
+/// Whether a path relative to the analyzed root is an analyzed source file
+fn is_source_file(
+    relative: &Path,
+    config: &Config,
+) -> bool {
+    let in_skipped_dir = relative
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .any(|c| is_skipped_dir(&c.as_os_str().to_string_lossy()));
+    relative.extension().is_some_and(|ext| ext == "rs")
+        && !in_skipped_dir
+        && !config.is_excluded(relative)
+}
+
+fn output_text(functions: &[analyzer::FunctionInfo]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "\n{:-<100}", "");
+    let _ = writeln!(
+        out,
         "{:<60} {:>8} {:>8} {:>8} {:>10}",
         "Function", "Start", "End", "Count", "Density"
     );
-    println!("{:-<100}", "");
+    let _ = writeln!(out, "{:-<100}", "");
 
     for func in functions {
-        println!(
+        let _ = writeln!(
+            out,
             "{:<60} {:>8} {:>8} {:>8} {:>10.2}",
             truncate(&func.full_path(), 60),
             func.start_line,
@@ -136,16 +422,21 @@ fn output_text(functions: &[analyzer::FunctionInfo]) {
             func.density()
         );
     }
+    out
 }
 
-fn output_json(functions: &[analyzer::FunctionInfo]) {
-    println!("{}", serde_json::to_string_pretty(functions).unwrap());
+fn output_json(functions: &[analyzer::FunctionInfo]) -> String {
+    format!("{}\n", serde_json::to_string_pretty(functions).unwrap())
 }
 
-fn output_csv(functions: &[analyzer::FunctionInfo]) {
-    println!("file,module_path,name,start_line,end_line,tracing_count,density");
+fn output_csv(functions: &[analyzer::FunctionInfo]) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "file,module_path,name,start_line,end_line,tracing_count,density\n",
+    );
     for func in functions {
-        println!(
+        let _ = writeln!(
+            out,
             "{},{},{},{},{},{},{:.4}",
             func.file.display(),
             func.module_path,
@@ -156,9 +447,11 @@ fn output_csv(functions: &[analyzer::FunctionInfo]) {
             func.density()
         );
     }
+    out
 }
 
-fn print_summary(functions: &[analyzer::FunctionInfo]) {
+fn summary(functions: &[analyzer::FunctionInfo]) -> String {
+    let mut out = String::new();
     let total_functions = functions.len();
     let total_tracing: usize = functions.iter().map(|f| f.tracing_count).sum();
     let zero_count = functions.iter().filter(|f| f.tracing_count == 0).count();
@@ -170,13 +463,14 @@ fn print_summary(functions: &[analyzer::FunctionInfo]) {
         0.0
     };
 
-    println!("\n{:=<60}", "");
-    println!("SUMMARY");
-    println!("{:=<60}", "");
-    println!("Total functions analyzed: {}", total_functions);
-    println!("Total tracing statements: {}", total_tracing);
-    println!("Total function lines:     {}", total_lines);
-    println!(
+    let _ = writeln!(out, "\n{:=<60}", "");
+    let _ = writeln!(out, "SUMMARY");
+    let _ = writeln!(out, "{:=<60}", "");
+    let _ = writeln!(out, "Total functions analyzed: {}", total_functions);
+    let _ = writeln!(out, "Total tracing statements: {}", total_tracing);
+    let _ = writeln!(out, "Total function lines:     {}", total_lines);
+    let _ = writeln!(
+        out,
         "Functions with 0 traces:  {} ({:.1}%)",
         zero_count,
         if total_functions > 0 {
@@ -185,7 +479,123 @@ fn print_summary(functions: &[analyzer::FunctionInfo]) {
             0.0
         }
     );
-    println!("Average density:          {:.2}%", avg_density);
+    let _ = writeln!(out, "Average density:          {:.2}%", avg_density);
+    out
+}
+
+fn runtime_coverage(functions: &[analyzer::FunctionInfo]) -> String {
+    let mut out = String::new();
+    let instrumented: Vec<_> = functions
+        .iter()
+        .filter(|f| f.has_instrument() || f.tracing_count > 0)
+        .collect();
+    let never_hit: Vec<_> = instrumented
+        .iter()
+        .filter(|f| f.runtime_hits == Some(0))
+        .collect();
+
+    let _ = writeln!(out, "\n{:=<60}", "");
+    let _ = writeln!(out, "RUNTIME COVERAGE");
+    let _ = writeln!(out, "{:=<60}", "");
+    for func in &never_hit {
+        let _ = writeln!(
+            out,
+            "never hit  {:<60} {}:{}",
+            truncate(&func.full_path(), 60),
+            func.file.display(),
+            func.start_line
+        );
+    }
+    let hit = instrumented.len() - never_hit.len();
+    let _ = writeln!(
+        out,
+        "Instrumented functions hit at runtime: {} of {} ({:.1}%)",
+        hit,
+        instrumented.len(),
+        if instrumented.is_empty() {
+            0.0
+        } else {
+            (hit as f64) / (instrumented.len() as f64) * 100.0
+        }
+    );
+    let untraced_hits = functions
+        .iter()
+        .filter(|f| !f.has_instrument() && f.tracing_count == 0)
+        .filter(|f| f.runtime_hits.is_some_and(|hits| hits > 0))
+        .count();
+    if untraced_hits > 0 {
+        let _ = writeln!(
+            out,
+            "Uninstrumented functions with runtime records: {}",
+            untraced_hits
+        );
+    }
+    out
+}
+
+fn budget_violations(violations: &[budget::BudgetViolation]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "\n{:=<60}", "");
+    let _ = writeln!(out, "SPAN DURATION BUDGETS");
+    let _ = writeln!(out, "{:=<60}", "");
+    if violations.is_empty() {
+        let _ = writeln!(out, "All measured spans within budget");
+    }
+    for violation in violations {
+        let _ = writeln!(
+            out,
+            "over budget  {:<50} max {:?} > {:?} ({} of {} spans)",
+            truncate(&violation.function, 50),
+            violation.max,
+            violation.budget,
+            violation.over,
+            violation.total
+        );
+    }
+    out
+}
+
+fn density_targets(targets: &[config::DensityTarget]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "\n{:=<60}", "");
+    let _ = writeln!(out, "DENSITY TARGETS");
+    let _ = writeln!(out, "{:=<60}", "");
+    for target in targets {
+        let Some(density) = target.density else {
+            let _ = writeln!(
+                out,
+                "{:<4} {:<40} no functions (min {:.2}%)",
+                "-",
+                truncate(&target.pattern, 40),
+                target.target
+            );
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "{:<4} {:<40} {:>6.2}% (min {:.2}%, {} functions)",
+            if target.is_met() { "ok" } else { "FAIL" },
+            truncate(&target.pattern, 40),
+            density,
+            target.target,
+            target.functions
+        );
+    }
+    out
+}
+
+fn baseline_regressions(regressions: &[baseline::Regression]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "\n{:=<60}", "");
+    let _ = writeln!(out, "BASELINE REGRESSIONS");
+    let _ = writeln!(out, "{:=<60}", "");
+    if regressions.is_empty() {
+        let _ = writeln!(out, "No regressions against baseline");
+    }
+    for regression in regressions {
+        let _ = writeln!(out, "{}", regression);
+    }
+    out
 }
 
 fn truncate(
@@ -198,3 +608,19 @@ fn truncate(
         format!("...{}", &s[s.len() - max_len + 3..])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_single_file() {
+        let file = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/main.rs");
+        let files = collect_source_files(&file, &Config::default());
+        assert_eq!(files, vec![file.clone()]);
+        assert!(!analyze_file(&files[0], 1).unwrap().is_empty());
+
+        let config = Config::parse(r#"exclude = ["main.rs"]"#).unwrap();
+        assert!(collect_source_files(&file, &config).is_empty());
+    }
+}
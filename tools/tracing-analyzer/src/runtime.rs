@@ -0,0 +1,416 @@
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+        HashMap,
+    },
+    fs,
+    path::{
+        Component,
+        Path,
+        PathBuf,
+    },
+    time::Duration,
+};
+
+use serde_json::Value;
+
+use crate::{
+    analyzer::FunctionInfo,
+    budget::parse_duration,
+};
+
+/// A single entry of a JSON tracing log (as parsed by the log-viewer)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogRecord {
+    /// Source file of the event or span callsite
+    pub filename: Option<String>,
+    /// Source line of the event or span callsite
+    pub line_number: Option<usize>,
+    /// Name of the innermost span the event belongs to
+    pub span: Option<String>,
+    /// `fields.message` of the event
+    pub message: Option<String>,
+    /// `fields."time.busy"` of a span close event
+    pub busy: Option<Duration>,
+}
+
+impl LogRecord {
+    fn from_value(value: &Value) -> Self {
+        Self {
+            filename: value
+                .get("filename")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            line_number: value
+                .get("line_number")
+                .and_then(Value::as_u64)
+                .map(|line| line as usize),
+            span: value.get("span").and_then(span_name).or_else(|| {
+                value
+                    .get("spans")
+                    .and_then(Value::as_array)
+                    .and_then(|spans| spans.last())
+                    .and_then(span_name)
+            }),
+            message: value
+                .pointer("/fields/message")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            busy: value
+                .get("fields")
+                .and_then(|fields| fields.get("time.busy"))
+                .and_then(Value::as_str)
+                .and_then(parse_duration),
+        }
+    }
+}
+
+/// Span entries are either plain names or objects with a `name` field
+fn span_name(value: &Value) -> Option<String> {
+    match value {
+        Value::String(name) => Some(name.clone()),
+        Value::Object(span) =>
+            span.get("name").and_then(Value::as_str).map(str::to_string),
+        _ => None,
+    }
+}
+
+/// Read all records of a JSON log file
+///
+/// Accepts both newline-delimited and concatenated pretty-printed JSON
+/// objects, the two layouts written by the workspace's tracing setup.
+/// Returns the records and the number of skipped unparsable records.
+pub fn read_log(path: &Path) -> Result<(Vec<LogRecord>, usize), String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read log: {}", e))?;
+    Ok(parse_log(&content))
+}
+
+/// Parse all records of JSON log content, skipping unparsable ones
+///
+/// Logs of crashed runs often end in a truncated record. After a parse
+/// error, parsing resumes at the next line starting with `{`. Returns the
+/// records and the number of skipped records.
+pub fn parse_log(content: &str) -> (Vec<LogRecord>, usize) {
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    let mut rest = content;
+    loop {
+        let mut values =
+            serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+        match values.next() {
+            None => break,
+            Some(Ok(value)) => {
+                records.push(LogRecord::from_value(&value));
+                rest = &rest[values.byte_offset()..];
+            },
+            Some(Err(_)) => {
+                skipped += 1;
+                let record = rest.trim_start();
+                rest =
+                    record.find("\n{").map_or("", |next| &record[next + 1..]);
+            },
+        }
+    }
+    (records, skipped)
+}
+
+/// Log filenames and span names that matched several functions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ambiguities {
+    /// Log filenames matching several analyzed files equally well
+    pub files: Vec<String>,
+    /// Span names shared by several instrumented functions
+    pub spans: Vec<String>,
+}
+
+/// Attribute log records to functions and store hit counts and durations
+///
+/// Records with a source location are attributed to the innermost
+/// function containing that line. Records without a location, or whose
+/// file can't be resolved, fall back to matching their span name against
+/// instrumented function names. Span close records additionally
+/// contribute their busy time.
+///
+/// Log filenames resolve to the analyzed file sharing the longest path
+/// suffix with them. Filenames matching several files equally well
+/// (e.g. `src/lib.rs` in a multi-crate tree) stay unresolved, as do span
+/// names shared by several functions (e.g. `new`). Records matching only
+/// ambiguously are not attributed at all; the ambiguous names are
+/// returned, sorted, so they can be reported.
+pub fn correlate(
+    functions: &mut [FunctionInfo],
+    records: &[LogRecord],
+) -> Ambiguities {
+    let mut by_file: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+    let mut by_span: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, func) in functions.iter().enumerate() {
+        by_file
+            .entry(normalize(&func.file))
+            .or_default()
+            .push(index);
+        if func.has_instrument() {
+            by_span.entry(func.name.as_str()).or_default().push(index);
+        }
+    }
+
+    // Resolve every distinct log filename to an analyzed file once
+    let mut resolved: HashMap<&str, Option<&PathBuf>> = HashMap::new();
+    let mut ambiguous_files = BTreeSet::new();
+    let mut ambiguous_spans = BTreeSet::new();
+    let mut hits = vec![0; functions.len()];
+    let mut durations = vec![Vec::new(); functions.len()];
+    let mut attribute = |index: usize, record: &LogRecord| {
+        hits[index] += 1;
+        if let Some(busy) = record.busy {
+            durations[index].push(busy);
+        }
+    };
+    for record in records {
+        let location = record.filename.as_deref().zip(record.line_number);
+        if let Some((filename, line)) = location {
+            let file = *resolved.entry(filename).or_insert_with(|| {
+                let log_path = normalize(Path::new(filename));
+                match longest_suffix_matches(by_file.keys(), &log_path)[..] {
+                    [file] => Some(file),
+                    [] => None,
+                    _ => {
+                        ambiguous_files.insert(filename.to_string());
+                        None
+                    },
+                }
+            });
+            let innermost = file.and_then(|file| {
+                by_file[file]
+                    .iter()
+                    .copied()
+                    .filter(|&i| {
+                        (functions[i].start_line..=functions[i].end_line)
+                            .contains(&line)
+                    })
+                    .min_by_key(|&i| functions[i].line_count())
+            });
+            if let Some(index) = innermost {
+                attribute(index, record);
+                continue;
+            }
+        }
+        let Some(span) = record.span.as_deref() else {
+            continue;
+        };
+        match by_span.get(span).map(Vec::as_slice) {
+            Some(&[index]) => attribute(index, record),
+            Some(_) => {
+                ambiguous_spans.insert(span.to_string());
+            },
+            None => {},
+        }
+    }
+
+    for ((func, hits), durations) in
+        functions.iter_mut().zip(hits).zip(durations)
+    {
+        func.runtime_hits = Some(hits);
+        func.span_durations = durations;
+    }
+    Ambiguities {
+        files: ambiguous_files.into_iter().collect(),
+        spans: ambiguous_spans.into_iter().collect(),
+    }
+}
+
+/// Files sharing the longest path suffix with `log_path`
+///
+/// A file matches if either path ends with the other; the match length
+/// is the number of components of the shorter one.
+fn longest_suffix_matches<'a>(
+    files: impl Iterator<Item = &'a PathBuf>,
+    log_path: &Path,
+) -> Vec<&'a PathBuf> {
+    let mut longest = 0;
+    let mut matches = Vec::new();
+    for file in files {
+        if !(file.ends_with(log_path) || log_path.ends_with(file)) {
+            continue;
+        }
+        let len = file.components().count().min(log_path.components().count());
+        if len > longest {
+            longest = len;
+            matches.clear();
+        }
+        if len == longest {
+            matches.push(file);
+        }
+    }
+    matches
+}
+
+/// Drop `.` components so relative and prefixed paths compare equal
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::InstrumentAttr;
+
+    fn function(
+        file: &str,
+        name: &str,
+        lines: (usize, usize),
+        instrument_attr: Option<InstrumentAttr>,
+    ) -> FunctionInfo {
+        FunctionInfo {
+            instrument_attr,
+            ..FunctionInfo::new(file, "", name, lines.0, lines.1)
+        }
+    }
+
+    #[test]
+    fn test_parse_log_formats() {
+        let (records, skipped) = parse_log(
+            r#"{
+  "fields": { "message": "enter" },
+  "span": { "name": "search" },
+  "filename": "src/search.rs",
+  "line_number": 12
+}
+{"fields":{"message":"done"},"spans":["insert"]}
+"#,
+        );
+        assert_eq!(skipped, 0);
+        assert_eq!(
+            records,
+            vec![
+                LogRecord {
+                    filename: Some("src/search.rs".to_string()),
+                    line_number: Some(12),
+                    span: Some("search".to_string()),
+                    message: Some("enter".to_string()),
+                    busy: None,
+                },
+                LogRecord {
+                    filename: None,
+                    line_number: None,
+                    span: Some("insert".to_string()),
+                    message: Some("done".to_string()),
+                    busy: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_log_skips_broken_records() {
+        let (records, skipped) = parse_log(
+            r#"{"fields":{"message":"a"}}
+{"fields":{"message": oops}}
+{
+  "fields": { "message": "b" }
+}
+{"fields":{"message":"trunc"#,
+        );
+        let messages: Vec<_> = records
+            .iter()
+            .map(|r| r.message.as_deref().unwrap())
+            .collect();
+        assert_eq!(messages, vec!["a", "b"]);
+        assert_eq!(skipped, 2);
+    }
+
+    #[test]
+    fn test_correlate() {
+        let mut functions = vec![
+            function(
+                "./crate/src/search.rs",
+                "search",
+                (10, 30),
+                Some(InstrumentAttr::Instrument),
+            ),
+            function("./crate/src/search.rs", "helper", (15, 20), None),
+            function(
+                "./crate/src/insert.rs",
+                "insert",
+                (1, 10),
+                Some(InstrumentAttr::Instrument),
+            ),
+            function(
+                "./crate/src/insert.rs",
+                "unused",
+                (12, 20),
+                Some(InstrumentAttr::Instrument),
+            ),
+        ];
+        let record =
+            |filename: Option<&str>, line, span: Option<&str>| LogRecord {
+                filename: filename.map(str::to_string),
+                line_number: line,
+                span: span.map(str::to_string),
+                message: None,
+                busy: None,
+            };
+        let ambiguous = correlate(
+            &mut functions,
+            &[
+                record(Some("crates/crate/src/search.rs"), Some(11), None),
+                record(Some("crates/crate/src/search.rs"), Some(16), None),
+                record(None, None, Some("insert")),
+            ],
+        );
+        let hits: Vec<_> =
+            functions.iter().map(|f| f.runtime_hits.unwrap()).collect();
+        assert_eq!(hits, vec![1, 1, 1, 0]);
+        assert_eq!(ambiguous, Ambiguities::default());
+    }
+
+    #[test]
+    fn test_correlate_resolves_longest_suffix() {
+        let mut functions = vec![
+            function("./a/src/lib.rs", "a", (1, 10), None),
+            function("./b/src/lib.rs", "b", (1, 10), None),
+            function("./x/src/lib.rs", "x", (1, 10), None),
+            function("./y/x/src/lib.rs", "yx", (1, 10), None),
+        ];
+        let record = |filename: &str| LogRecord {
+            filename: Some(filename.to_string()),
+            line_number: Some(5),
+            ..LogRecord::default()
+        };
+        let ambiguous = correlate(
+            &mut functions,
+            &[
+                record("src/lib.rs"),
+                record("crates/b/src/lib.rs"),
+                record("y/x/src/lib.rs"),
+            ],
+        );
+        let hits: Vec<_> =
+            functions.iter().map(|f| f.runtime_hits.unwrap()).collect();
+        assert_eq!(hits, vec![0, 1, 0, 1]);
+        assert_eq!(ambiguous.files, vec!["src/lib.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_correlate_ambiguous_span_names() {
+        let instrumented = Some(InstrumentAttr::Instrument);
+        let mut functions = vec![
+            function("./a.rs", "new", (1, 10), instrumented),
+            function("./b.rs", "new", (1, 10), instrumented),
+            function("./b.rs", "find", (12, 20), instrumented),
+        ];
+        let record = |span: &str| LogRecord {
+            span: Some(span.to_string()),
+            ..LogRecord::default()
+        };
+        let ambiguous =
+            correlate(&mut functions, &[record("new"), record("find")]);
+        let hits: Vec<_> =
+            functions.iter().map(|f| f.runtime_hits.unwrap()).collect();
+        assert_eq!(hits, vec![0, 0, 1]);
+        assert_eq!(ambiguous.spans, vec!["new".to_string()]);
+    }
+}
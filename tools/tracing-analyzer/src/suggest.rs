@@ -0,0 +1,186 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::Path,
+};
+
+use crate::analyzer::FunctionInfo;
+
+/// Lines of unchanged context around each insertion
+const CONTEXT: usize = 3;
+
+/// A suggested attribute line inserted before a 1-indexed source line
+#[derive(Debug, Clone, PartialEq)]
+struct Insertion {
+    line: usize,
+    attribute: String,
+}
+
+/// Build `#[instrument_sig]` insertions for every zero-coverage function
+///
+/// Returns a unified diff (applicable with `git apply`) adding the
+/// attribute above each uninstrumented function that has no tracing
+/// statements. No `use` items are added, since the import path of
+/// `instrument_sig` depends on the crate being patched.
+pub fn unified_diff(functions: &[FunctionInfo]) -> Result<String, String> {
+    let mut files: BTreeMap<&Path, Vec<&FunctionInfo>> = BTreeMap::new();
+    for func in functions
+        .iter()
+        .filter(|f| f.tracing_count == 0 && !f.has_instrument())
+    {
+        files.entry(func.file.as_path()).or_default().push(func);
+    }
+
+    let mut diff = String::new();
+    for (file, funcs) in files {
+        let content = fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {:?}: {}", file, e))?;
+        let insertions = funcs
+            .iter()
+            .map(|func| Insertion {
+                line: func.item_line,
+                attribute: attribute(&func.skip_params),
+            })
+            .collect();
+        diff.push_str(&file_diff(file, &content, insertions));
+    }
+    Ok(diff)
+}
+
+fn attribute(skip_params: &[String]) -> String {
+    if skip_params.is_empty() {
+        "#[instrument_sig]".to_string()
+    } else {
+        format!("#[instrument_sig(skip({}))]", skip_params.join(", "))
+    }
+}
+
+/// Render the diff of one file for the given insertions
+fn file_diff(
+    file: &Path,
+    content: &str,
+    mut insertions: Vec<Insertion>,
+) -> String {
+    insertions.sort_by_key(|insertion| insertion.line);
+    insertions.dedup_by_key(|insertion| insertion.line);
+
+    // Keep line endings so the diff applies to CRLF files as well
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let path = file.display().to_string().replace('\\', "/");
+    let path = path.trim_start_matches("./");
+
+    let mut out = String::new();
+    let _ = writeln!(out, "--- a/{}", path);
+    let _ = writeln!(out, "+++ b/{}", path);
+
+    // Group insertions whose context windows touch into one hunk
+    let mut hunks: Vec<Vec<&Insertion>> = Vec::new();
+    for insertion in &insertions {
+        match hunks.last_mut() {
+            Some(hunk)
+                if insertion.line - hunk.last().unwrap().line
+                    <= 2 * CONTEXT =>
+                hunk.push(insertion),
+            _ => hunks.push(vec![insertion]),
+        }
+    }
+
+    let mut added = 0;
+    for hunk in hunks {
+        let first = hunk.first().unwrap().line;
+        let last = hunk.last().unwrap().line;
+        let old_start = first.saturating_sub(CONTEXT).max(1);
+        let old_end = (last - 1 + CONTEXT).min(lines.len());
+        let old_count = old_end + 1 - old_start;
+        let new_count = old_count + hunk.len();
+        let _ = writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            old_start,
+            old_count,
+            old_start + added,
+            new_count
+        );
+
+        let mut pending = hunk.iter().peekable();
+        for (index, line) in lines[old_start - 1..old_end].iter().enumerate() {
+            let number = old_start + index;
+            if let Some(insertion) = pending.next_if(|i| i.line == number) {
+                let indent: String = line
+                    .chars()
+                    .take_while(|c| *c == ' ' || *c == '\t')
+                    .collect();
+                let ending = if line.ends_with("\r\n") { "\r\n" } else { "\n" };
+                let _ =
+                    write!(out, "+{}{}{}", indent, insertion.attribute, ending);
+            }
+            out.push(' ');
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        added += hunk.len();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insertion(
+        line: usize,
+        attribute: &str,
+    ) -> Insertion {
+        Insertion {
+            line,
+            attribute: attribute.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_attribute() {
+        assert_eq!(attribute(&[]), "#[instrument_sig]");
+        assert_eq!(
+            attribute(&["self".to_string(), "trav".to_string()]),
+            "#[instrument_sig(skip(self, trav))]"
+        );
+    }
+
+    #[test]
+    fn test_file_diff_merges_close_insertions() {
+        let content = "fn a() {}\nfn b() {}\n\n\n\n\n\n\n\n\nfn c() {}\n";
+        let diff = file_diff(
+            Path::new("./src/lib.rs"),
+            content,
+            vec![
+                insertion(2, "#[b]"),
+                insertion(1, "#[a]"),
+                insertion(11, "#[c]"),
+            ],
+        );
+        assert_eq!(
+            diff,
+            "--- a/src/lib.rs\n\
+             +++ b/src/lib.rs\n\
+             @@ -1,4 +1,6 @@\n\
+             +#[a]\n fn a() {}\n+#[b]\n fn b() {}\n \n \n\
+             @@ -8,4 +10,5 @@\n \n \n \n+#[c]\n fn c() {}\n"
+        );
+    }
+
+    #[test]
+    fn test_file_diff_keeps_indentation() {
+        let content = "impl A {\r\n    fn a(&self) {}\r\n}\r\n";
+        let diff = file_diff(
+            Path::new("a.rs"),
+            content,
+            vec![insertion(2, "#[instrument_sig(skip(self))]")],
+        );
+        assert!(diff.contains(
+            "+    #[instrument_sig(skip(self))]\r\n     fn a(&self) {}\r\n"
+        ));
+    }
+}
@@ -0,0 +1,256 @@
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    sync::mpsc,
+    time::Duration,
+};
+
+use notify::{
+    RecursiveMode,
+    Watcher,
+};
+
+use crate::analyzer::FunctionInfo;
+
+/// Time to wait for further events after a change before re-analyzing
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Change of a single function between two analyses of its file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delta {
+    Added {
+        name: String,
+        count: usize,
+    },
+    Removed {
+        name: String,
+        count: usize,
+    },
+    Changed {
+        name: String,
+        before: usize,
+        after: usize,
+        density: f64,
+    },
+}
+
+impl std::fmt::Display for Delta {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        match self {
+            Delta::Added { name, count } =>
+                write!(f, "+ {} ({} statements)", name, count),
+            Delta::Removed { name, count } =>
+                write!(f, "- {} ({} statements)", name, count),
+            Delta::Changed {
+                name,
+                before,
+                after,
+                density,
+            } => write!(
+                f,
+                "~ {} ({} -> {} statements, {:.2}%)",
+                name, before, after, density
+            ),
+        }
+    }
+}
+
+/// Compare two analyses of the same file
+///
+/// Functions are matched by qualified path; only added, removed and
+/// functions whose tracing count changed are reported.
+pub fn deltas(
+    before: &[FunctionInfo],
+    after: &[FunctionInfo],
+) -> Vec<Delta> {
+    let before: BTreeMap<String, &FunctionInfo> =
+        before.iter().map(|f| (f.full_path(), f)).collect();
+    let after: BTreeMap<String, &FunctionInfo> =
+        after.iter().map(|f| (f.full_path(), f)).collect();
+
+    let mut deltas = Vec::new();
+    for (name, new) in &after {
+        match before.get(name) {
+            None => deltas.push(Delta::Added {
+                name: name.clone(),
+                count: new.tracing_count,
+            }),
+            Some(old) if old.tracing_count != new.tracing_count =>
+                deltas.push(Delta::Changed {
+                    name: name.clone(),
+                    before: old.tracing_count,
+                    after: new.tracing_count,
+                    density: new.density(),
+                }),
+            Some(_) => {},
+        }
+    }
+    for (name, old) in &before {
+        if !after.contains_key(name) {
+            deltas.push(Delta::Removed {
+                name: name.clone(),
+                count: old.tracing_count,
+            });
+        }
+    }
+    deltas
+}
+
+/// Watch `root` and print coverage deltas for every changed source file
+///
+/// `state` holds the initial analysis per file, `analyze` runs the
+/// (filtered) analysis of a single file and `is_source` decides whether
+/// a path relative to `root` is analyzed. Runs until the process is
+/// interrupted.
+pub fn run(
+    root: &Path,
+    mut state: BTreeMap<PathBuf, Vec<FunctionInfo>>,
+    analyze: impl Fn(&Path) -> Result<Vec<FunctionInfo>, String>,
+    is_source: impl Fn(&Path) -> bool,
+) -> Result<(), String> {
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {:?}: {}", root, e))?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| format!("Failed to start watcher: {}", e))?;
+    watcher
+        .watch(&canonical_root, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {:?}: {}", root, e))?;
+
+    println!("\nWatching {} for changes (Ctrl-C to stop)", root.display());
+    print_totals(&state);
+
+    while let Ok(event) = rx.recv() {
+        let mut changed = BTreeSet::new();
+        let mut collect = |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event {
+                changed.extend(event.paths);
+            }
+        };
+        collect(event);
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            collect(event);
+        }
+
+        let mut any = false;
+        for path in changed {
+            let Ok(relative) = path.strip_prefix(&canonical_root) else {
+                continue;
+            };
+            if !is_source(relative) {
+                continue;
+            }
+            let file = root.join(relative);
+            let before = state.remove(&file).unwrap_or_default();
+            let after = if file.is_file() {
+                match analyze(&file) {
+                    Ok(functions) => functions,
+                    Err(e) => {
+                        // Keep the last good state while the file is broken
+                        eprintln!("Error analyzing {:?}: {}", file, e);
+                        state.insert(file, before);
+                        continue;
+                    },
+                }
+            } else {
+                Vec::new()
+            };
+
+            let file_deltas = deltas(&before, &after);
+            if !file_deltas.is_empty() {
+                any = true;
+                println!("\n{}", file.display());
+                for delta in file_deltas {
+                    println!("  {}", delta);
+                }
+            }
+            if !after.is_empty() {
+                state.insert(file, after);
+            }
+        }
+        if any {
+            print_totals(&state);
+        }
+    }
+    Ok(())
+}
+
+fn print_totals(state: &BTreeMap<PathBuf, Vec<FunctionInfo>>) {
+    let functions = state.values().flatten();
+    let (count, tracing, lines, zero) =
+        functions.fold((0, 0, 0, 0), |(count, tracing, lines, zero), f| {
+            (
+                count + 1,
+                tracing + f.tracing_count,
+                lines + f.line_count(),
+                zero + usize::from(f.tracing_count == 0),
+            )
+        });
+    let density = if lines > 0 {
+        (tracing as f64) / (lines as f64) * 100.0
+    } else {
+        0.0
+    };
+    println!(
+        "[{} functions, {} statements, {} with 0 traces, {:.2}% density]",
+        count, tracing, zero, density
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn function(
+        name: &str,
+        tracing_count: usize,
+    ) -> FunctionInfo {
+        FunctionInfo {
+            tracing_count,
+            ..FunctionInfo::new("lib.rs", "lib", name, 1, 10)
+        }
+    }
+
+    #[test]
+    fn test_deltas() {
+        let before = [
+            function("kept", 1),
+            function("traced", 0),
+            function("gone", 2),
+        ];
+        let after = [
+            function("kept", 1),
+            function("traced", 2),
+            function("new", 0),
+        ];
+        assert_eq!(
+            deltas(&before, &after),
+            vec![
+                Delta::Added {
+                    name: "lib::new".to_string(),
+                    count: 0,
+                },
+                Delta::Changed {
+                    name: "lib::traced".to_string(),
+                    before: 0,
+                    after: 2,
+                    density: 20.0,
+                },
+                Delta::Removed {
+                    name: "lib::gone".to_string(),
+                    count: 2,
+                },
+            ]
+        );
+    }
+}